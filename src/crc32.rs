@@ -0,0 +1,105 @@
+//! Minimal table-based CRC-32 (IEEE 802.3 polynomial), used to detect
+//! corruption or truncation of an encoded payload on decode.
+
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+const fn build_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut byte = 0;
+
+    while byte < 256 {
+        let mut crc = byte as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                POLYNOMIAL ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+
+        table[byte] = crc;
+        byte += 1;
+    }
+
+    table
+}
+
+const TABLE: [u32; 256] = build_table();
+
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    let mut builder = Crc32Builder::new();
+    builder.update_slice(bytes);
+    builder.finish()
+}
+
+/// Incremental CRC-32 accumulator, so a streaming caller can verify a
+/// checksum as bytes are read instead of buffering the whole frame first.
+pub(crate) struct Crc32Builder {
+    crc: u32,
+}
+
+impl Default for Crc32Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crc32Builder {
+    pub(crate) fn new() -> Self {
+        Self { crc: 0xFFFF_FFFF }
+    }
+
+    pub(crate) fn update(&mut self, byte: u8) {
+        let index = ((self.crc ^ byte as u32) & 0xFF) as usize;
+        self.crc = TABLE[index] ^ (self.crc >> 8);
+    }
+
+    pub(crate) fn update_slice(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.update(byte);
+        }
+    }
+
+    pub(crate) fn finish(self) -> u32 {
+        self.crc ^ 0xFFFF_FFFF
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_empty() {
+        assert_eq!(crc32(&[]), 0);
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        // The standard CRC-32 check value for the ASCII string "123456789".
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn crc32_builder_matches_one_shot() {
+        let data = b"shh steganography payload";
+        let mut builder = Crc32Builder::new();
+        for &byte in data {
+            builder.update(byte);
+        }
+
+        assert_eq!(builder.finish(), crc32(data));
+    }
+
+    #[test]
+    fn crc32_detects_single_bit_flip() {
+        let original = b"shh steganography payload".to_vec();
+        let mut corrupted = original.clone();
+        corrupted[3] ^= 0b0000_0001;
+
+        assert_ne!(crc32(&original), crc32(&corrupted));
+    }
+}
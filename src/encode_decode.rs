@@ -1,84 +1,415 @@
 use anyhow::{anyhow, Result};
 use image::{DynamicImage, GenericImageView, ImageBuffer, Rgb};
+use rayon::prelude::*;
+use std::io::Write;
+
+use crate::{
+    crc32::Crc32Builder,
+    rlp, scatter,
+    stream::{PayloadReader, PayloadSource, StreamError},
+    Payload, BOOTSTRAP_LEN, FORMAT_VERSION, MAGIC,
+};
+
+/// Embeds `bits` least-significant bits of the container per image byte
+/// (1 to 4). Everything past the fixed-width bootstrap (magic, version,
+/// this value itself, scatter flag) uses this depth, so `decode_image` learns it from
+/// the header instead of the caller having to repeat it. When `key` is
+/// set, the container is scattered across pseudorandom chunk positions
+/// (see [`crate::scatter`]) instead of packed sequentially, and the same
+/// key is required to decode it.
+pub fn encode_image(
+    input_image: &DynamicImage,
+    payload: Payload,
+    bits: u8,
+    key: Option<&str>,
+) -> Result<DynamicImage> {
+    if !(1..=4).contains(&bits) {
+        return Err(anyhow!("bit depth must be between 1 and 4, got {bits}"));
+    }
 
-use crate::Payload;
-
-pub fn encode_image(input_image: &DynamicImage, payload: Payload) -> Result<DynamicImage> {
     let payload_size = payload.size();
 
-    if !payload_fits(payload_size, image_rgb_bytes_size(input_image)) {
+    if !payload_fits(payload_size, bits, image_rgb_bytes_size(input_image)) {
         return Err(anyhow!(
-            "The payload is too big to be encoded in the input image. Choose a bigger image (in resolution) or compress the payload."
+            "The payload is too big to be encoded in the input image. Choose a bigger image (in resolution), compress the payload, or raise the bit depth."
         ));
     }
 
-    let payload_bytes = payload.into_bytes()?;
+    let payload_bytes = payload.into_bytes(bits, key.is_some())?;
 
     let (width, height) = input_image.dimensions();
-    let image_bytes = input_image.to_rgb8().into_raw();
-    let chunks = create_byte_chunks(&image_bytes).take(payload_size);
-
-    // Encode the payload
-    let mut output: Vec<u8> = payload_bytes
-        .iter()
-        .zip(chunks)
-        .flat_map(|(payload, chunk)| encode_byte_in_bytes(chunk, payload))
-        .collect();
-
-    output.reserve(image_bytes.len() - output.len());
-
-    // Fill the rest of the image with the original bytes
-    for byte in image_bytes.into_iter().skip(output.len()) {
-        output.push(byte);
+    let mut image_bytes = input_image.to_rgb8().into_raw();
+
+    // The bootstrap fields (magic, version, bit depth, scatter flag) are
+    // always embedded at 1 bit/byte so a decoder can read them before it
+    // knows `bits`; the rest of the container uses the configured depth,
+    // either packed sequentially or scattered via a key-seeded permutation.
+    let (bootstrap, rest) = payload_bytes.split_at(BOOTSTRAP_LEN);
+    let bootstrap_image_len = BOOTSTRAP_LEN * chunk_width(1);
+    let (bootstrap_bytes, rest_bytes) = image_bytes.split_at_mut(bootstrap_image_len);
+
+    encode_parallel(bootstrap, bootstrap_bytes, 1);
+
+    match key {
+        Some(key) => {
+            let available_slots = rest_bytes.len() / chunk_width(bits);
+            let perm = scatter::permutation(available_slots, key.as_bytes());
+            encode_scattered(rest, rest_bytes, bits, &perm);
+        }
+        None => encode_parallel(rest, rest_bytes, bits),
     }
 
     let image_buffer: ImageBuffer<Rgb<u8>, Vec<u8>> =
-        ImageBuffer::from_raw(width, height, output).unwrap();
+        ImageBuffer::from_raw(width, height, image_bytes).unwrap();
 
     Ok(DynamicImage::ImageRgb8(image_buffer))
 }
 
-pub fn decode_image(image: &DynamicImage) -> Result<(String, Vec<u8>)> {
+pub fn decode_image(image: &DynamicImage, key: Option<&str>) -> Result<(String, Vec<u8>)> {
+    let mut payload = Vec::new();
+    let file_name = decode_image_to(image, &mut payload, key)?;
+    Ok((file_name, payload))
+}
+
+/// Decodes the payload hidden in `image` and writes it to `output` in one
+/// call, returning its original file name once the trailing checksum has
+/// been verified. The payload itself is decoded in parallel across chunks
+/// (see [`decode_parallel`]/[`decode_scattered`]), which means it's fully
+/// assembled in memory before this ever calls `output.write_all` — `output`
+/// is a `Write` for caller convenience (a file, a `Vec`, ...), not a memory
+/// bound. `key` must match the passphrase used at encode time if the image
+/// was scattered.
+pub fn decode_image_to<W: Write>(
+    image: &DynamicImage,
+    output: &mut W,
+    key: Option<&str>,
+) -> Result<String> {
     let image_bytes = image.to_rgb8().into_raw();
+    Ok(decode_stream(&image_bytes, output, key)?)
+}
 
-    let mut chunks = create_byte_chunks(&image_bytes);
+/// Embeds `payload` into the first `payload.len() * chunk_width(bits)`
+/// bytes of `image_bytes`, in place. Each payload byte maps to a fixed,
+/// disjoint chunk of that width, so chunks are encoded in parallel across
+/// threads via rayon.
+fn encode_parallel(payload: &[u8], image_bytes: &mut [u8], bits: u8) {
+    let width = chunk_width(bits);
+    let used = payload.len() * width;
+    let (target, _unused) = image_bytes.split_at_mut(used);
+
+    target
+        .par_chunks_exact_mut(width)
+        .zip(payload.par_iter())
+        .for_each(|(chunk, &byte)| encode_byte_in_bytes(chunk, byte, bits));
+}
 
-    let file_name_size: u16 = u16::from_le_bytes(
-        <[u8; 2]>::try_from(decode_chunks(&mut chunks, 2))
-            .map_err(|_| anyhow!("This image probably wasn't encoded. It's too small to contain the encoded file name"))?,
-    );
+/// Decodes a run of fixed-width image chunks into payload bytes in
+/// parallel, the mirror of [`encode_parallel`]: each payload byte lives at
+/// a fixed, independent offset, so chunks can be decoded out of order
+/// across threads and collected back by index.
+fn decode_parallel(image_bytes: &[u8], bits: u8) -> Vec<u8> {
+    image_bytes
+        .par_chunks_exact(chunk_width(bits))
+        .map(|chunk| decode_byte(chunk, bits))
+        .collect()
+}
 
-    let file_name = String::from_utf8(decode_chunks(&mut chunks, file_name_size as usize))
-        .map_err(|_| {
-            anyhow!("This image probably wasn't encoded. The file name is not valid UTF-8")
-        })?;
+/// Embeds `payload` into `image_bytes` at permuted chunk positions instead
+/// of sequential ones: logical byte `i` lands at chunk `perm[i]`. The
+/// permutation is inverted into a per-slot lookup first so every chunk can
+/// still be processed independently in parallel, the same way
+/// [`encode_parallel`] does for the sequential case.
+fn encode_scattered(payload: &[u8], image_bytes: &mut [u8], bits: u8, perm: &[usize]) {
+    let width = chunk_width(bits);
+
+    let mut payload_index_for_slot: Vec<Option<usize>> = vec![None; perm.len()];
+    for (i, &slot) in perm.iter().enumerate().take(payload.len()) {
+        payload_index_for_slot[slot] = Some(i);
+    }
 
-    let payload_size: u64 = u64::from_le_bytes(
-        <[u8; 8]>::try_from(decode_chunks(&mut chunks, 8))
-            .map_err(|_| anyhow!("This image probably wasn't encoded. It's too small to contain the encoded payload size"))?,
-    );
+    image_bytes
+        .par_chunks_exact_mut(width)
+        .zip(payload_index_for_slot.par_iter())
+        .for_each(|(chunk, slot)| {
+            if let Some(&i) = slot.as_ref() {
+                encode_byte_in_bytes(chunk, payload[i], bits);
+            }
+        });
+}
 
-    let payload_size: usize = u64_to_usize(payload_size)?;
-    let payload = decode_chunks(&mut chunks, payload_size);
+/// Decodes `count` logical payload bytes starting at logical index
+/// `offset`, each read from its permuted chunk position in `image_bytes`
+/// (the mirror of [`encode_scattered`]).
+fn decode_scattered(
+    image_bytes: &[u8],
+    bits: u8,
+    perm: &[usize],
+    offset: usize,
+    count: usize,
+) -> Result<Vec<u8>, StreamError> {
+    let width = chunk_width(bits);
+    let too_small = || {
+        StreamError::Syntax(
+            "this image is too small to contain the encoded payload and checksum".to_string(),
+        )
+    };
+
+    let end = offset.checked_add(count).ok_or_else(too_small)?;
+    let slots = perm.get(offset..end).ok_or_else(too_small)?;
+
+    slots
+        .par_iter()
+        .map(|&slot| {
+            let start = slot.checked_mul(width).ok_or_else(too_small)?;
+            let chunk = image_bytes
+                .get(start..start + width)
+                .ok_or_else(too_small)?;
+            Ok(decode_byte(chunk, bits))
+        })
+        .collect()
+}
 
-    if payload.len() < payload_size {
-        return Err(anyhow!(
-            "This image probably wasn't encoded. The encoded length is smaller then expected"
+/// Reads payload bytes out of `rest` in permuted order: logical byte `i`
+/// lives at chunk `perm[i]` instead of chunk `i` (see [`crate::scatter`]).
+/// The mirror of [`PayloadReader`] for scattered containers, used so the
+/// RLP header-parsing helpers below can stay oblivious to scatter mode.
+struct ScatteredReader<'a> {
+    rest: &'a [u8],
+    perm: &'a [usize],
+    bits: u8,
+    cursor: usize,
+    peeked: Option<u8>,
+}
+
+impl<'a> ScatteredReader<'a> {
+    fn new(rest: &'a [u8], perm: &'a [usize], bits: u8) -> Self {
+        Self {
+            rest,
+            perm,
+            bits,
+            cursor: 0,
+            peeked: None,
+        }
+    }
+
+    fn read_chunk(&mut self) -> Result<u8, StreamError> {
+        let width = chunk_width(self.bits);
+        let slot = *self.perm.get(self.cursor).ok_or(StreamError::Eof)?;
+        let start = slot * width;
+        let chunk = self
+            .rest
+            .get(start..start + width)
+            .ok_or(StreamError::Eof)?;
+        self.cursor += 1;
+        Ok(decode_byte(chunk, self.bits))
+    }
+}
+
+impl PayloadSource for ScatteredReader<'_> {
+    fn peek_byte(&mut self) -> Result<u8, StreamError> {
+        if let Some(byte) = self.peeked {
+            return Ok(byte);
+        }
+
+        let byte = self.read_chunk()?;
+        self.peeked = Some(byte);
+        Ok(byte)
+    }
+
+    fn read_byte(&mut self) -> Result<u8, StreamError> {
+        if let Some(byte) = self.peeked.take() {
+            return Ok(byte);
+        }
+
+        self.read_chunk()
+    }
+}
+
+/// Parses the container out of `image_bytes` lazily: checks the magic and
+/// version before touching the length-prefixed fields. The bit depth and
+/// scatter mode used for everything past the bootstrap are read from the
+/// header itself, so callers never have to pass the former back in; `key`
+/// is only needed to regenerate the scatter permutation, and is required
+/// if the header says the container is scattered. Once the payload's
+/// bounds are known, it's decoded in parallel (see [`decode_parallel`]/
+/// [`decode_scattered`]) into a single in-memory `Vec` — this does not
+/// bound memory on large payloads, it only parallelizes the decode — then
+/// written to `out` in one `write_all`, and the trailing CRC-32 is
+/// verified.
+fn decode_stream<W: Write>(
+    image_bytes: &[u8],
+    out: &mut W,
+    key: Option<&str>,
+) -> Result<String, StreamError> {
+    // The bootstrap (magic, version, bit depth, scatter flag) is always 1
+    // bit/image-byte, since the layout for the rest of the container isn't
+    // known yet.
+    let mut reader = PayloadReader::new(image_bytes, 1);
+    let mut crc = Crc32Builder::new();
+
+    let magic = reader.read_vec(MAGIC.len())?;
+    if magic != MAGIC {
+        return Err(StreamError::Syntax(
+            "this doesn't look like an shh image".to_string(),
         ));
     }
+    crc.update_slice(&magic);
 
-    Ok((file_name, payload))
+    let version = reader.read_byte()?;
+    if version != FORMAT_VERSION {
+        return Err(StreamError::Syntax(
+            "this shh image was encoded with an unsupported container version".to_string(),
+        ));
+    }
+    crc.update(version);
+
+    let bits = reader.read_byte()?;
+    if !(1..=4).contains(&bits) {
+        return Err(StreamError::Syntax(format!(
+            "unsupported bit depth in container header: {bits}"
+        )));
+    }
+    crc.update(bits);
+
+    let scattered = reader.read_byte()?;
+    if scattered > 1 {
+        return Err(StreamError::Syntax(format!(
+            "unsupported scatter flag in container header: {scattered}"
+        )));
+    }
+    crc.update(scattered);
+    let scattered = scattered == 1;
+
+    let key = match (scattered, key) {
+        (true, None) => {
+            return Err(StreamError::Syntax(
+                "this image was encoded with a key; pass --key to decode it".to_string(),
+            ))
+        }
+        (true, Some(key)) => Some(key),
+        (false, _) => None,
+    };
+
+    let bootstrap_image_len = BOOTSTRAP_LEN * chunk_width(1);
+    let width = chunk_width(bits);
+
+    let rest = image_bytes.get(bootstrap_image_len..).unwrap_or_default();
+    let perm = key.map(|key| scatter::permutation(rest.len() / width, key.as_bytes()));
+
+    let (file_name, payload_len, chunks_read) = match &perm {
+        Some(perm) => {
+            let mut reader = ScatteredReader::new(rest, perm, bits);
+            parse_header(&mut reader, &mut crc)?
+        }
+        None => {
+            let mut reader = PayloadReader::new(rest, bits);
+            parse_header(&mut reader, &mut crc)?
+        }
+    };
+
+    let too_small = || {
+        StreamError::Syntax(
+            "this image is too small to contain the encoded payload and checksum".to_string(),
+        )
+    };
+
+    let (payload, checksum_bytes): (Vec<u8>, Vec<u8>) = match &perm {
+        Some(perm) => (
+            decode_scattered(rest, bits, perm, chunks_read, payload_len)?,
+            decode_scattered(rest, bits, perm, chunks_read + payload_len, 4)?,
+        ),
+        None => {
+            let payload_start = chunks_read.checked_mul(width).ok_or_else(too_small)?;
+            let payload_chunks_len = payload_len.checked_mul(width).ok_or_else(too_small)?;
+            let payload_end = payload_start
+                .checked_add(payload_chunks_len)
+                .ok_or_else(too_small)?;
+            let checksum_end = payload_end.checked_add(4 * width).ok_or_else(too_small)?;
+
+            let payload_bytes = rest.get(payload_start..payload_end).ok_or_else(too_small)?;
+            let checksum_bytes = rest.get(payload_end..checksum_end).ok_or_else(too_small)?;
+
+            (
+                decode_parallel(payload_bytes, bits),
+                checksum_bytes
+                    .chunks_exact(width)
+                    .map(|chunk| decode_byte(chunk, bits))
+                    .collect(),
+            )
+        }
+    };
+    crc.update_slice(&payload);
+    out.write_all(&payload).map_err(StreamError::Io)?;
+    let checksum = u32::from_le_bytes(<[u8; 4]>::try_from(checksum_bytes.as_slice()).unwrap());
+
+    if crc.finish() != checksum {
+        return Err(StreamError::Syntax(
+            "checksum mismatch: the encoded payload is corrupted or was truncated".to_string(),
+        ));
+    }
+
+    Ok(file_name)
 }
 
-fn decode_chunks<'a, I>(chunks: &mut I, count: usize) -> Vec<u8>
-where
-    I: Iterator<Item = &'a [u8; 8]>,
-{
-    chunks
-        .by_ref()
-        .take(count)
-        .map(decode_byte)
-        .collect::<Vec<u8>>()
+/// Reads the RLP-style file name and payload length out of `reader`,
+/// accumulating both into `crc`. Generic over [`PayloadSource`] so it works
+/// whether `reader` addresses image bytes sequentially or through a
+/// scatter permutation. Returns the chunk count consumed by these two
+/// fields, so the caller can locate the payload that follows them.
+fn parse_header<S: PayloadSource>(
+    reader: &mut S,
+    crc: &mut Crc32Builder,
+) -> Result<(String, usize, usize), StreamError> {
+    let file_name_bytes = read_rlp_bytes(reader, crc)?;
+    let mut chunks_read = rlp::prefix_len(file_name_bytes.len()) + file_name_bytes.len();
+    let file_name = String::from_utf8(file_name_bytes)
+        .map_err(|_| StreamError::Syntax("the encoded file name is not valid UTF-8".to_string()))?;
+
+    let payload_len = read_rlp_len(reader, crc)?;
+    chunks_read += rlp::prefix_len(payload_len);
+
+    Ok((file_name, payload_len, chunks_read))
+}
+
+/// Reads an RLP-style length prefix (see [`crate::rlp`]), peeking the lead
+/// byte first so it can be validated before it's consumed.
+fn read_rlp_len<S: PayloadSource>(
+    reader: &mut S,
+    crc: &mut Crc32Builder,
+) -> Result<usize, StreamError> {
+    let lead = reader.peek_byte()?;
+    if !(0x80..=0xBF).contains(&lead) {
+        return Err(StreamError::Syntax(format!(
+            "invalid length prefix byte: {lead:#x}"
+        )));
+    }
+    reader.read_byte()?;
+    crc.update(lead);
+
+    if lead <= 0xB7 {
+        Ok((lead - 0x80) as usize)
+    } else {
+        let n = (lead - 0xB7) as usize;
+        let len_bytes = reader.read_vec(n)?;
+        crc.update_slice(&len_bytes);
+
+        let mut padded = [0u8; 8];
+        let start = padded.len().checked_sub(len_bytes.len()).ok_or_else(|| {
+            StreamError::Syntax("length prefix is too big for this platform".to_string())
+        })?;
+        padded[start..].copy_from_slice(&len_bytes);
+        u64_to_usize(u64::from_be_bytes(padded))
+    }
+}
+
+fn read_rlp_bytes<S: PayloadSource>(
+    reader: &mut S,
+    crc: &mut Crc32Builder,
+) -> Result<Vec<u8>, StreamError> {
+    let len = read_rlp_len(reader, crc)?;
+    let data = reader.read_vec(len)?;
+    crc.update_slice(&data);
+    Ok(data)
 }
 
 fn image_rgb_bytes_size(image: &DynamicImage) -> usize {
@@ -87,59 +418,69 @@ fn image_rgb_bytes_size(image: &DynamicImage) -> usize {
     width as usize * height as usize * 3
 }
 
-fn u64_to_usize(value: u64) -> Result<usize> {
+fn u64_to_usize(value: u64) -> Result<usize, StreamError> {
     if value <= usize::MAX as u64 {
         Ok(value as usize)
     } else {
-        Err(anyhow!(
-            "Payload size {} is too big for this platform",
-            value
-        ))
+        Err(StreamError::Syntax(format!(
+            "payload size {value} is too big for this platform"
+        )))
     }
 }
 
-fn encode_byte_in_bytes(target: &[u8; 8], payload: &u8) -> [u8; 8] {
-    let mut mask: u8 = 0b0000_0001;
-    let mut result: [u8; 8] = [0; 8];
-
-    for i in 0..8 {
-        let current_bit = payload & mask;
-
-        let encoded = if current_bit != 0 {
-            target[i] | 0b0000_0001
-        } else {
-            target[i] & 0b1111_1110
-        };
+/// Number of image bytes one payload byte spans when packing `bits`
+/// least-significant bits per image byte: `ceil(8 / bits)`.
+pub(crate) fn chunk_width(bits: u8) -> usize {
+    8usize.div_ceil(bits as usize)
+}
 
-        result[i] = encoded;
-        mask <<= 1;
+/// Packs `bits` least-significant bits of `payload` into each byte of
+/// `target` in place, zeroing the low `bits` bits of each target byte and
+/// ORing in the next slice of payload bits. The last byte may consume
+/// fewer than `bits` bits if `8` isn't a multiple of `bits`. Mutates in
+/// place rather than allocating a new `Vec` since this runs once per
+/// payload byte across potentially millions of chunks.
+pub(crate) fn encode_byte_in_bytes(target: &mut [u8], payload: u8, bits: u8) {
+    let mut consumed = 0u8;
+
+    for byte in target.iter_mut() {
+        let take = bits.min(8 - consumed);
+        let mask = (1u8 << take) - 1;
+        let bits_value = (payload >> consumed) & mask;
+        consumed += take;
+        *byte = (*byte & !mask) | bits_value;
     }
-
-    result
 }
 
-fn decode_byte(encoded: &[u8; 8]) -> u8 {
-    let mask: u8 = 0b0000_0001;
-
-    let mut decoded: u8 = 0;
-
-    for (i, byte) in encoded.iter().enumerate().take(8) {
-        decoded |= (mask & byte) << i;
+/// Reassembles a payload byte from `bits` least-significant bits of each
+/// byte of `encoded`, the mirror of [`encode_byte_in_bytes`].
+pub(crate) fn decode_byte(encoded: &[u8], bits: u8) -> u8 {
+    let mut decoded = 0u8;
+    let mut consumed = 0u8;
+
+    for &byte in encoded {
+        let take = bits.min(8 - consumed);
+        let mask = (1u8 << take) - 1;
+        decoded |= (byte & mask) << consumed;
+        consumed += take;
     }
 
     decoded
 }
 
-fn create_byte_chunks(image_bytes: &[u8]) -> impl Iterator<Item = &[u8; 8]> {
-    image_bytes
-        .chunks_exact(8)
-        .map(|chunk| chunk.try_into().unwrap())
+/// Total image bytes needed to embed a `payload_size`-byte container at
+/// the given bit depth: the fixed-width bootstrap plus the rest at
+/// `chunk_width(bits)` image bytes per byte.
+fn image_bytes_needed(payload_size: usize, bits: u8) -> Option<usize> {
+    let variable_len = payload_size.checked_sub(BOOTSTRAP_LEN)?;
+    let bootstrap_image_len = BOOTSTRAP_LEN.checked_mul(chunk_width(1))?;
+    let variable_image_len = variable_len.checked_mul(chunk_width(bits))?;
+    bootstrap_image_len.checked_add(variable_image_len)
 }
 
-fn payload_fits(payload_size: usize, image_rgb_size: usize) -> bool {
-    payload_size
-        .checked_mul(8)
-        .map(|it| it <= image_rgb_size)
+fn payload_fits(payload_size: usize, bits: u8, image_rgb_size: usize) -> bool {
+    image_bytes_needed(payload_size, bits)
+        .map(|needed| needed <= image_rgb_size)
         .unwrap_or(false)
 }
 
@@ -150,7 +491,7 @@ mod tests {
 
     #[test]
     fn encode_byte_all_zeros() {
-        let target: [u8; 8] = [
+        let mut target: [u8; 8] = [
             0b1010_0000,
             0b1001_0110,
             0b0100_0100,
@@ -173,13 +514,14 @@ mod tests {
             0b1000_1001,
         ];
 
-        assert_eq!(encoded, encode_byte_in_bytes(&target, &payload));
-        assert_eq!(decode_byte(&encoded), payload);
+        encode_byte_in_bytes(&mut target, payload, 1);
+        assert_eq!(target, encoded);
+        assert_eq!(decode_byte(&encoded, 1), payload);
     }
 
     #[test]
     fn encode_byte_all_ones() {
-        let target: [u8; 8] = [
+        let mut target: [u8; 8] = [
             0b0110_0001,
             0b0111_0111,
             0b0000_0101,
@@ -202,13 +544,14 @@ mod tests {
             0b1110_1000,
         ];
 
-        assert_eq!(encoded, encode_byte_in_bytes(&target, &payload));
-        assert_eq!(decode_byte(&encoded), payload);
+        encode_byte_in_bytes(&mut target, payload, 1);
+        assert_eq!(target, encoded);
+        assert_eq!(decode_byte(&encoded, 1), payload);
     }
 
     #[test]
     fn encode_byte_random() {
-        let target: [u8; 8] = [
+        let mut target: [u8; 8] = [
             0b0010_0000,
             0b0001_0111,
             0b0000_0101,
@@ -231,35 +574,36 @@ mod tests {
             0b1000_1001,
         ];
 
-        assert_eq!(encoded, encode_byte_in_bytes(&target, &payload));
-        assert_eq!(decode_byte(&encoded), payload);
+        encode_byte_in_bytes(&mut target, payload, 1);
+        assert_eq!(target, encoded);
+        assert_eq!(decode_byte(&encoded, 1), payload);
     }
 
     #[test]
     fn encode_byte_payload_all_zeros() {
-        let target: [u8; 8] = [0b0101_0101; 8];
+        let mut target: [u8; 8] = [0b0101_0101; 8];
         let payload: u8 = 0b0000_0000;
-        let encoded = encode_byte_in_bytes(&target, &payload);
-        assert_eq!(encoded, [0b0101_0100; 8]);
-        assert_eq!(decode_byte(&encoded), payload);
+        encode_byte_in_bytes(&mut target, payload, 1);
+        assert_eq!(target, [0b0101_0100; 8]);
+        assert_eq!(decode_byte(&target, 1), payload);
     }
 
     #[test]
     fn encode_byte_payload_all_ones() {
-        let target: [u8; 8] = [0b0101_0100; 8];
+        let mut target: [u8; 8] = [0b0101_0100; 8];
         let payload: u8 = 0b1111_1111;
-        let encoded = encode_byte_in_bytes(&target, &payload);
-        assert_eq!(encoded, [0b0101_0101; 8]);
-        assert_eq!(decode_byte(&encoded), payload);
+        encode_byte_in_bytes(&mut target, payload, 1);
+        assert_eq!(target, [0b0101_0101; 8]);
+        assert_eq!(decode_byte(&target, 1), payload);
     }
 
     #[test]
     fn encode_byte_payload_mixed() {
-        let target: [u8; 8] = [0b0101_0100; 8];
+        let mut target: [u8; 8] = [0b0101_0100; 8];
         let payload: u8 = 0b1010_1010;
-        let encoded = encode_byte_in_bytes(&target, &payload);
+        encode_byte_in_bytes(&mut target, payload, 1);
         assert_eq!(
-            encoded,
+            target,
             [
                 0b0101_0100,
                 0b0101_0101,
@@ -271,35 +615,155 @@ mod tests {
                 0b0101_0101
             ]
         );
-        assert_eq!(decode_byte(&encoded), payload);
+        assert_eq!(decode_byte(&target, 1), payload);
+    }
+
+    #[test]
+    fn encode_byte_in_bytes_packs_multiple_bits_per_image_byte() {
+        // At 4 bits/image-byte, a payload byte spans 2 image bytes: low
+        // nibble first, then the high nibble.
+        let mut target: [u8; 2] = [0b1111_0000, 0b1111_0000];
+        let payload: u8 = 0b1010_0110;
+
+        encode_byte_in_bytes(&mut target, payload, 4);
+
+        assert_eq!(target, [0b1111_0110, 0b1111_1010]);
+        assert_eq!(decode_byte(&target, 4), payload);
+    }
+
+    #[test]
+    fn encode_byte_in_bytes_handles_bit_depth_not_dividing_eight() {
+        // At 3 bits/image-byte, a payload byte spans 3 image bytes: 3 + 3 + 2
+        // bits, low-to-high.
+        let mut target: [u8; 3] = [0, 0, 0];
+        let payload: u8 = 0b1011_0110;
+
+        encode_byte_in_bytes(&mut target, payload, 3);
+
+        assert_eq!(target, [0b0000_0110, 0b0000_0110, 0b0000_0010]);
+        assert_eq!(decode_byte(&target, 3), payload);
+    }
+
+    #[test]
+    fn encode_parallel_round_trips_through_decode_parallel() {
+        let payload: Vec<u8> = (0..=255).collect();
+        let mut image_bytes = vec![0u8; payload.len() * 8 + 5];
+
+        encode_parallel(&payload, &mut image_bytes, 1);
+
+        let decoded = decode_parallel(&image_bytes[..payload.len() * 8], 1);
+        assert_eq!(decoded, payload);
+    }
+
+    #[test]
+    fn encode_parallel_round_trips_at_higher_bit_depth() {
+        let payload: Vec<u8> = (0..=255).collect();
+        let width = chunk_width(3);
+        let mut image_bytes = vec![0u8; payload.len() * width + 5];
+
+        encode_parallel(&payload, &mut image_bytes, 3);
+
+        let decoded = decode_parallel(&image_bytes[..payload.len() * width], 3);
+        assert_eq!(decoded, payload);
     }
 
     #[test]
-    fn test_payload_fits() {
-        assert!(payload_fits(1, 72));
-        assert!(payload_fits(100, 1000000));
-        assert!(payload_fits(1000, 8065));
-        assert!(!payload_fits(1000, 8063));
-        assert!(!payload_fits(usize::MAX, usize::MAX));
-        assert!(payload_fits(usize::MAX / 8 - 9, usize::MAX));
-        assert!(!payload_fits(usize::MAX / 8 - 7, usize::MAX));
+    fn encode_parallel_leaves_unused_tail_untouched() {
+        let payload = vec![0xAAu8];
+        let mut image_bytes = vec![7u8; 16];
+
+        encode_parallel(&payload, &mut image_bytes, 1);
+
+        assert_eq!(&image_bytes[8..], &[7u8; 8]);
+    }
+
+    #[test]
+    fn test_payload_fits_one_bit() {
+        // BOOTSTRAP_LEN bytes at 8 image bytes each, plus 10 variable bytes
+        // at 8 image bytes each.
+        let needed = (BOOTSTRAP_LEN + 10) * 8;
+        assert!(payload_fits(BOOTSTRAP_LEN + 10, 1, needed));
+        assert!(!payload_fits(BOOTSTRAP_LEN + 10, 1, needed - 1));
+        assert!(!payload_fits(usize::MAX, 1, usize::MAX));
+    }
+
+    #[test]
+    fn test_payload_fits_scales_with_bit_depth() {
+        // Same container as above, but at 4 bits/image-byte each variable
+        // byte only costs 2 image bytes instead of 8.
+        let needed = BOOTSTRAP_LEN * 8 + 10 * 2;
+        assert!(payload_fits(BOOTSTRAP_LEN + 10, 4, needed));
+        assert!(!payload_fits(BOOTSTRAP_LEN + 10, 4, needed - 1));
+    }
+
+    #[test]
+    fn test_payload_fits_rejects_undersized_container() {
+        assert!(!payload_fits(BOOTSTRAP_LEN - 1, 1, usize::MAX));
     }
 
     #[test]
     fn decode_byte_all_zeros() {
         let encoded: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 0];
-        assert_eq!(decode_byte(&encoded), 0);
+        assert_eq!(decode_byte(&encoded, 1), 0);
     }
 
     #[test]
     fn decode_byte_all_ones() {
         let encoded: [u8; 8] = [11, 19, 101, 17, 25, 1, 13, 1];
-        assert_eq!(decode_byte(&encoded), 0b1111_1111);
+        assert_eq!(decode_byte(&encoded, 1), 0b1111_1111);
     }
 
     #[test]
     fn decode_byte_mixed() {
         let encoded: [u8; 8] = [8, 1, 12, 13, 78, 236, 116, 11];
-        assert_eq!(decode_byte(&encoded), 0b1000_1010);
+        assert_eq!(decode_byte(&encoded, 1), 0b1000_1010);
+    }
+
+    #[test]
+    fn encode_scattered_round_trips_through_decode_scattered() {
+        let payload = vec![0xDEu8, 0xAD, 0xBE, 0xEF];
+        let perm = scatter::permutation(payload.len() * 3, b"hunter2");
+        let mut image_bytes = vec![0u8; perm.len() * chunk_width(1)];
+
+        encode_scattered(&payload, &mut image_bytes, 1, &perm);
+        let decoded = decode_scattered(&image_bytes, 1, &perm, 0, payload.len()).unwrap();
+
+        assert_eq!(decoded, payload);
+    }
+
+    #[test]
+    fn encode_scattered_does_not_pack_payload_at_the_front() {
+        // With this key and slot count, the permutation doesn't start with
+        // an ascending run, so a sequentially-packed payload would land
+        // differently than a scattered one.
+        let payload = vec![0xFFu8; 8];
+        let perm = scatter::permutation(64, b"hunter2");
+        let mut scattered_bytes = vec![0u8; perm.len() * chunk_width(1)];
+        let mut sequential_bytes = scattered_bytes.clone();
+
+        encode_scattered(&payload, &mut scattered_bytes, 1, &perm);
+        encode_parallel(&payload, &mut sequential_bytes, 1);
+
+        assert_ne!(scattered_bytes, sequential_bytes);
+    }
+
+    #[test]
+    fn decode_scattered_reports_too_small_past_the_permutation() {
+        let perm = scatter::permutation(4, b"hunter2");
+        let image_bytes = vec![0u8; perm.len() * chunk_width(1)];
+
+        assert!(decode_scattered(&image_bytes, 1, &perm, 0, perm.len() + 1).is_err());
+    }
+
+    #[test]
+    fn decode_stream_requires_key_for_scattered_containers() {
+        let payload = Payload::Literal("hi".to_string());
+        let payload_bytes = payload.into_bytes(1, true).unwrap();
+        let mut image_bytes = vec![0u8; payload_bytes.len() * chunk_width(1) * 4];
+        encode_parallel(&payload_bytes, &mut image_bytes, 1);
+
+        let mut out = Vec::new();
+        let err = decode_stream(&image_bytes, &mut out, None).unwrap_err();
+        assert!(matches!(err, StreamError::Syntax(_)));
     }
 }
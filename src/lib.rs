@@ -6,6 +6,24 @@ use std::fs::{self};
 use std::path::{Path, PathBuf};
 
 pub mod encode_decode;
+mod crc32;
+mod rlp;
+mod scatter;
+mod stream;
+
+/// Fixed lead bytes identifying an shh container, checked before anything
+/// else on decode so a non-shh image is rejected immediately.
+pub(crate) const MAGIC: [u8; 4] = *b"SHH1";
+
+/// Container format version. Bumped whenever the header layout changes so
+/// `decode_image` can refuse images it no longer knows how to parse.
+pub(crate) const FORMAT_VERSION: u8 = 1;
+
+/// Number of leading container bytes (magic, version, bit depth, scatter
+/// flag) that are always embedded 1 bit per image byte, since neither the
+/// bit depth nor the chunk layout for everything after them is known until
+/// this prefix has been read.
+pub(crate) const BOOTSTRAP_LEN: usize = MAGIC.len() + 1 + 1 + 1;
 
 #[derive(Parser)]
 #[command(author, version, about = "Shh: simple Rust steganography")]
@@ -28,6 +46,19 @@ pub enum Commands {
         /// Output file name (always saved as PNG)
         #[arg(default_value = "encoded.png")]
         output: String,
+
+        /// Bits per channel byte to encode into (1-4). Higher values trade
+        /// visual fidelity for capacity; the value is stored in the image
+        /// so it never needs to be passed to `decode`.
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=4))]
+        bits: u8,
+
+        /// Optional passphrase. When set, the payload is scattered across
+        /// pseudorandom chunk positions (seeded from the key) instead of
+        /// packed sequentially at the start of the image, and the same key
+        /// is required to decode it.
+        #[arg(long)]
+        key: Option<String>,
     },
 
     /// Decode payload from an image.
@@ -38,6 +69,11 @@ pub enum Commands {
 
         /// Optional. Output file name for the extracted payload. The original file extension is preserved.
         output: Option<String>,
+
+        /// Passphrase used at encode time. Required if the image was
+        /// encoded with `--key`.
+        #[arg(long)]
+        key: Option<String>,
     },
 }
 
@@ -58,10 +94,13 @@ pub enum Operation {
         target_image: DynamicImage,
         payload: Payload,
         output_path: PathBuf,
+        bits: u8,
+        key: Option<String>,
     },
     Decode {
         encoded_image: DynamicImage,
         output_path: Option<PathBuf>,
+        key: Option<String>,
     },
 }
 
@@ -77,6 +116,8 @@ impl Config {
                 target_image,
                 payload,
                 output,
+                bits,
+                key,
             } => {
                 let input_image = read_image(&target_image)?;
 
@@ -102,17 +143,21 @@ impl Config {
                     target_image: input_image,
                     payload: payload_data,
                     output_path,
+                    bits,
+                    key,
                 }
             }
             Commands::Decode {
                 encoded_image,
                 output,
+                key,
             } => {
                 let input_image = read_image(&encoded_image)?;
                 let output_path = output.map(PathBuf::from);
                 Operation::Decode {
                     encoded_image: input_image,
                     output_path,
+                    key,
                 }
             }
         };
@@ -126,16 +171,19 @@ impl Config {
                 target_image,
                 payload,
                 output_path,
+                bits,
+                key,
             } => {
-                let encoded = encode_image(&target_image, payload)?;
+                let encoded = encode_image(&target_image, payload, bits, key.as_deref())?;
                 encoded.save(&output_path)?;
                 println!("Encoded image saved to '{}'", output_path.display());
             }
             Operation::Decode {
                 encoded_image,
                 output_path,
+                key,
             } => {
-                let (original_name, decoded) = decode_image(&encoded_image)?;
+                let (original_name, decoded) = decode_image(&encoded_image, key.as_deref())?;
 
                 let final_out_path = if let Some(output_path) = &output_path {
                     let original_ext = Path::new(&original_name)
@@ -161,29 +209,46 @@ impl Config {
 }
 
 impl Payload {
-    /// Extra 2 bytes for file name length, 8 bytes for payload size
+    /// [`BOOTSTRAP_LEN`] bytes of magic, version, bit depth and scatter
+    /// flag, an RLP-style length-prefixed file name, an RLP-style
+    /// length-prefixed payload, and a trailing 4-byte CRC-32.
     fn size(&self) -> usize {
-        match self {
-            Payload::File { bytes, file_name } => 2 + file_name.len() + 8 + bytes.len(),
-            Payload::Literal(string) => 2 + "output.txt".len() + 8 + string.len(),
-        }
+        let (name_len, payload_len) = match self {
+            Payload::File { bytes, file_name } => (file_name.len(), bytes.len()),
+            Payload::Literal(string) => ("output.txt".len(), string.len()),
+        };
+
+        BOOTSTRAP_LEN
+            + rlp::prefix_len(name_len)
+            + name_len
+            + rlp::prefix_len(payload_len)
+            + payload_len
+            + 4
     }
 
-    fn into_bytes(self) -> Result<Vec<u8>> {
+    /// Serializes the payload into the on-disk container: `MAGIC`, a format
+    /// version byte, the chosen LSB bit depth, a scatter flag, the
+    /// RLP-style length-prefixed name and payload fields, and finally a
+    /// CRC-32 of everything that came before it. `decode_image` checks the
+    /// magic and version before touching the length-prefixed fields, reads
+    /// the bit depth and scatter flag to know how to decode the rest, then
+    /// verifies the checksum to catch corruption or truncation.
+    fn into_bytes(self, bits: u8, scattered: bool) -> Result<Vec<u8>> {
         let (bytes, file_name) = match self {
             Payload::File { bytes, file_name } => (bytes, file_name),
             Payload::Literal(string) => (string.into_bytes(), "output.txt".to_string()),
         };
 
-        let name_len = (file_name.len() as u16).to_le_bytes();
-        let bytes_len = (bytes.len() as u64).to_le_bytes();
-
-        Ok(name_len
+        let framed: Vec<u8> = MAGIC
             .into_iter()
-            .chain(file_name.into_bytes())
-            .chain(bytes_len)
-            .chain(bytes)
-            .collect::<Vec<u8>>())
+            .chain([FORMAT_VERSION, bits, scattered as u8])
+            .chain(rlp::encode(file_name.as_bytes()))
+            .chain(rlp::encode(&bytes))
+            .collect();
+
+        let checksum = crc32::crc32(&framed).to_le_bytes();
+
+        Ok(framed.into_iter().chain(checksum).collect())
     }
 }
 
@@ -0,0 +1,97 @@
+//! RLP-style length-prefixed byte strings: a single lead byte encodes the
+//! length of short strings inline, and longer strings spend extra bytes on
+//! an explicit big-endian length, so typical small fields (file names,
+//! small payloads) cost a single header byte instead of a fixed-width one.
+//!
+//! Lead byte `0x80..=0xB7` means "inline length `lead - 0x80`" (0 to 55).
+//! Lead byte `0xB8..=0xBF` means "the next `lead - 0xB7` bytes are a
+//! big-endian length", for strings longer than 55 bytes.
+
+const SHORT_LIMIT: usize = 55;
+const SHORT_LEAD: u8 = 0x80;
+const LONG_LEAD: u8 = 0xB7;
+
+/// Number of bytes a length-prefixed encoding of `len` spends on the prefix
+/// alone (not counting the data itself).
+pub(crate) fn prefix_len(len: usize) -> usize {
+    if len <= SHORT_LIMIT {
+        1
+    } else {
+        1 + big_endian_len_bytes(len as u64)
+    }
+}
+
+/// Encodes `data` as a lead byte (plus, for long strings, explicit length
+/// bytes) followed by the data itself.
+pub(crate) fn encode(data: &[u8]) -> Vec<u8> {
+    let len = data.len();
+    let mut out = Vec::with_capacity(prefix_len(len) + len);
+
+    if len <= SHORT_LIMIT {
+        out.push(SHORT_LEAD + len as u8);
+    } else {
+        let len_bytes = len as u64;
+        let n = big_endian_len_bytes(len_bytes);
+        out.push(LONG_LEAD + n as u8);
+        out.extend_from_slice(&len_bytes.to_be_bytes()[8 - n..]);
+    }
+
+    out.extend_from_slice(data);
+    out
+}
+
+/// Number of big-endian bytes needed to represent `len`, for the long-form
+/// prefix (`len` is always `> 0` when this is called from [`encode`]/
+/// [`prefix_len`], since 0 falls in the short-form range).
+fn big_endian_len_bytes(len: u64) -> usize {
+    let used_bits = u64::BITS - len.leading_zeros();
+    (used_bits as usize).div_ceil(8).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_empty_string() {
+        assert_eq!(encode(b""), vec![0x80]);
+    }
+
+    #[test]
+    fn encodes_short_string() {
+        assert_eq!(encode(b"dog"), vec![0x83, b'd', b'o', b'g']);
+    }
+
+    #[test]
+    fn encodes_55_byte_string_inline() {
+        let data = vec![b'x'; 55];
+        let encoded = encode(&data);
+        assert_eq!(encoded[0], 0x80 + 55);
+        assert_eq!(&encoded[1..], data.as_slice());
+    }
+
+    #[test]
+    fn encodes_56_byte_string_with_one_length_byte() {
+        let data = vec![b'x'; 56];
+        let encoded = encode(&data);
+        assert_eq!(encoded[0], 0xB8);
+        assert_eq!(encoded[1], 56);
+        assert_eq!(&encoded[2..], data.as_slice());
+    }
+
+    #[test]
+    fn encodes_large_string_with_multiple_length_bytes() {
+        let data = vec![0u8; 70_000];
+        let encoded = encode(&data);
+        assert_eq!(encoded[0], 0xB7 + 3);
+        assert_eq!(&encoded[1..4], &[0x01, 0x11, 0x70]);
+        assert_eq!(encoded.len(), 4 + 70_000);
+    }
+
+    #[test]
+    fn prefix_len_matches_encode() {
+        for len in [0, 1, 55, 56, 300, 70_000] {
+            assert_eq!(prefix_len(len), encode(&vec![0u8; len]).len() - len);
+        }
+    }
+}
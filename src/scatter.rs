@@ -0,0 +1,101 @@
+//! Key-seeded pseudorandom permutation of chunk slots, used to scatter an
+//! encoded payload across the whole image instead of packing it into the
+//! first few kilobytes. Seeding a small deterministic PRNG from the
+//! passphrase lets `decode_image` regenerate the identical permutation
+//! without the container storing anything beyond a single "scattered" flag
+//! (see [`crate::encode_decode`]'s bootstrap header).
+//!
+//! This isn't meant to be cryptographically secure, only to defeat naive
+//! steganalysis that inspects the image prefix; a PRNG seeded from a
+//! passphrase hash is plenty for that.
+
+/// A small, fast, non-cryptographic PRNG (SplitMix64), used only to drive
+/// the shuffle below.
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A uniformly-ish distributed value in `0..bound`. `bound` is always
+    /// small here (at most the number of chunk slots in the image), so the
+    /// slight modulo bias isn't worth a rejection loop.
+    fn next_below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+/// Hashes an arbitrary-length passphrase down to a 64-bit PRNG seed
+/// (FNV-1a), so the same key always produces the same permutation.
+fn seed_from_key(key: &[u8]) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+    let mut hash = FNV_OFFSET;
+    for &byte in key {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Builds a bijection over `0..len` (a Fisher-Yates shuffle of the chunk
+/// indices) seeded from `key`. Logical payload byte `i` lives at chunk
+/// `permutation(len, key)[i]` instead of chunk `i`.
+pub(crate) fn permutation(len: usize, key: &[u8]) -> Vec<usize> {
+    let mut perm: Vec<usize> = (0..len).collect();
+    let mut rng = SplitMix64::new(seed_from_key(key));
+
+    for i in (1..len).rev() {
+        let j = rng.next_below(i + 1);
+        perm.swap(i, j);
+    }
+
+    perm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permutation_is_a_bijection() {
+        let perm = permutation(256, b"correct horse battery staple");
+        let mut seen = vec![false; 256];
+        for &slot in &perm {
+            assert!(!seen[slot], "slot {slot} used twice");
+            seen[slot] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+    }
+
+    #[test]
+    fn permutation_is_deterministic_for_the_same_key() {
+        let a = permutation(128, b"hunter2");
+        let b = permutation(128, b"hunter2");
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn permutation_differs_across_keys() {
+        let a = permutation(128, b"hunter2");
+        let b = permutation(128, b"hunter3");
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn permutation_of_empty_range_is_empty() {
+        assert_eq!(permutation(0, b"key"), Vec::<usize>::new());
+    }
+}
@@ -0,0 +1,193 @@
+//! Sequential streaming primitives for the small, order-dependent parts of
+//! the container (magic, version, length-prefixed fields): one payload
+//! byte at a time (`chunk_width(bits)` image bytes per payload byte) from
+//! any `Read`. `encode_decode` feeds these the image bytes it has already
+//! collected into memory, so they don't bound memory use there. The bulk
+//! payload, once its
+//! bounds are known, is decoded separately and in parallel (see
+//! `encode_decode::decode_parallel`/`encode_parallel`).
+
+use std::io::{self, Read};
+
+use crate::encode_decode::{chunk_width, decode_byte};
+
+/// Error surfaced by the streaming codec, distinguishing a clean
+/// end-of-stream from malformed input and I/O failures so callers can tell
+/// "ran out of bytes" apart from "these bytes don't make sense".
+#[derive(Debug)]
+pub(crate) enum StreamError {
+    /// The underlying stream ran out of bytes before a full chunk (one
+    /// payload byte, `ceil(8/bits)` image bytes) could be read.
+    Eof,
+    /// The bytes read didn't form a valid container (bad magic, version,
+    /// length prefix, checksum, etc).
+    Syntax(String),
+    /// The underlying reader/writer failed.
+    Io(io::Error),
+}
+
+impl std::fmt::Display for StreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StreamError::Eof => write!(f, "unexpected end of image data"),
+            StreamError::Syntax(message) => write!(f, "{message}"),
+            StreamError::Io(err) => write!(f, "I/O error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {}
+
+/// A source of payload bytes, one at a time, abstracting over how the
+/// underlying image bytes are addressed: sequentially (see
+/// [`PayloadReader`]) or through a [`crate::scatter`] permutation (see
+/// `encode_decode::ScatteredReader`). This is what lets the RLP
+/// header-parsing helpers in `encode_decode` stay oblivious to scatter mode.
+pub(crate) trait PayloadSource {
+    /// Returns the next payload byte without consuming it.
+    fn peek_byte(&mut self) -> Result<u8, StreamError>;
+
+    /// Reads and consumes the next payload byte.
+    fn read_byte(&mut self) -> Result<u8, StreamError>;
+
+    /// Reads `len` payload bytes into a freshly allocated `Vec`. `len` comes
+    /// straight off an untrusted RLP length prefix, so this must not
+    /// pre-reserve it as capacity: a crafted prefix near `u64::MAX` would
+    /// either overflow the allocator or attempt a multi-terabyte
+    /// allocation. Growing the `Vec` one `read_byte` at a time instead bounds
+    /// the allocation by however many bytes the source actually has, since a
+    /// truncated source fails with [`StreamError::Eof`] long before `len` is
+    /// reached.
+    fn read_vec(&mut self, len: usize) -> Result<Vec<u8>, StreamError> {
+        let mut bytes = Vec::new();
+        for _ in 0..len {
+            bytes.push(self.read_byte()?);
+        }
+        Ok(bytes)
+    }
+}
+
+fn read_chunk<R: Read>(reader: &mut R, chunk: &mut [u8]) -> Result<(), StreamError> {
+    reader.read_exact(chunk).map_err(|err| {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            StreamError::Eof
+        } else {
+            StreamError::Io(err)
+        }
+    })
+}
+
+/// Reads one payload byte at a time from an underlying image-byte stream,
+/// at a fixed bit depth (`bits` least-significant bits per image byte, so
+/// one payload byte spans `chunk_width(bits)` image bytes). Keeps a
+/// one-byte peek buffer so a header parser can inspect the next payload
+/// byte (e.g. an RLP lead byte) without consuming it.
+pub(crate) struct PayloadReader<R> {
+    inner: R,
+    bits: u8,
+    peeked: Option<u8>,
+}
+
+impl<R: Read> PayloadReader<R> {
+    pub(crate) fn new(inner: R, bits: u8) -> Self {
+        Self {
+            inner,
+            bits,
+            peeked: None,
+        }
+    }
+
+    fn read_chunk(&mut self) -> Result<u8, StreamError> {
+        let mut buf = [0u8; 8];
+        let width = chunk_width(self.bits);
+        read_chunk(&mut self.inner, &mut buf[..width])?;
+        Ok(decode_byte(&buf[..width], self.bits))
+    }
+
+    /// Returns the next payload byte without consuming it.
+    pub(crate) fn peek_byte(&mut self) -> Result<u8, StreamError> {
+        if let Some(byte) = self.peeked {
+            return Ok(byte);
+        }
+
+        let byte = self.read_chunk()?;
+        self.peeked = Some(byte);
+        Ok(byte)
+    }
+
+    /// Reads and consumes the next payload byte.
+    pub(crate) fn read_byte(&mut self) -> Result<u8, StreamError> {
+        if let Some(byte) = self.peeked.take() {
+            return Ok(byte);
+        }
+
+        self.read_chunk()
+    }
+}
+
+impl<R: Read> PayloadSource for PayloadReader<R> {
+    fn peek_byte(&mut self) -> Result<u8, StreamError> {
+        PayloadReader::peek_byte(self)
+    }
+
+    fn read_byte(&mut self) -> Result<u8, StreamError> {
+        PayloadReader::read_byte(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peek_byte_does_not_consume() {
+        let image_bytes = [1u8, 0, 1, 0, 1, 0, 1, 0];
+        let mut reader = PayloadReader::new(&image_bytes[..], 1);
+
+        assert_eq!(reader.peek_byte().unwrap(), 0b0101_0101);
+        assert_eq!(reader.peek_byte().unwrap(), 0b0101_0101);
+        assert_eq!(reader.read_byte().unwrap(), 0b0101_0101);
+    }
+
+    #[test]
+    fn read_byte_after_peek_does_not_reread() {
+        let image_bytes = [0u8; 16];
+        let mut reader = PayloadReader::new(&image_bytes[..], 1);
+
+        reader.peek_byte().unwrap();
+        assert_eq!(reader.read_byte().unwrap(), 0);
+        assert_eq!(reader.read_byte().unwrap(), 0);
+    }
+
+    #[test]
+    fn read_byte_reports_eof_on_truncated_chunk() {
+        let image_bytes = [0u8; 4];
+        let mut reader = PayloadReader::new(&image_bytes[..], 1);
+
+        assert!(matches!(reader.read_byte(), Err(StreamError::Eof)));
+    }
+
+    #[test]
+    fn read_vec_reports_eof_instead_of_over_allocating_on_a_huge_len() {
+        // A length this close to usize::MAX must never reach
+        // Vec::with_capacity: it should fail fast once the short image runs
+        // out of bytes instead of attempting a huge allocation.
+        let image_bytes = [0u8; 4];
+        let mut reader = PayloadReader::new(&image_bytes[..], 1);
+
+        assert!(matches!(
+            reader.read_vec(usize::MAX - 1),
+            Err(StreamError::Eof)
+        ));
+    }
+
+    #[test]
+    fn read_byte_at_higher_bit_depth_uses_narrower_chunks() {
+        // At 4 bits/image-byte, a payload byte spans 2 image bytes: the low
+        // nibble of each.
+        let image_bytes = [0b0000_1010u8, 0b0000_1100];
+        let mut reader = PayloadReader::new(&image_bytes[..], 4);
+
+        assert_eq!(reader.read_byte().unwrap(), 0b1100_1010);
+    }
+}